@@ -0,0 +1,344 @@
+//! A persistent, append-only on-disk store for computed embeddings.
+//!
+//! Lets a corpus be embedded once and then reloaded or streamed across process runs
+//! instead of recomputing through the ONNX session. Records are appended as a
+//! little-endian `u64` length prefix followed by a bincode-encoded payload; a separate
+//! in-memory `id -> byte offset` index (persisted alongside the data file on flush)
+//! lets [`EmbeddingStore::get`] fetch a single vector without scanning the whole file.
+
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Current on-disk format version, written as the first byte of the data file.
+const FORMAT_VERSION: u8 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    id: String,
+    embedding: Vec<f32>,
+}
+
+/// On-disk shape of the sidecar `.idx` file: the `id -> offset` map plus the byte
+/// offset into the data file that the map covers, so [`EmbeddingStore::open`] can tell
+/// whether records were appended after the last [`EmbeddingStore::flush`] and, if so,
+/// tail-scan just the uncovered bytes instead of trusting a stale map outright.
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedIndex {
+    covered_offset: u64,
+    index: HashMap<String, u64>,
+}
+
+/// A persistent, append-only store of `(id, Vec<f32>)` embedding records.
+///
+/// Keeps an in-memory `id -> byte offset` index so [`EmbeddingStore::get`] can fetch a
+/// single vector without scanning the data file. The index is rebuilt from disk on
+/// [`EmbeddingStore::open`] if no sidecar index file is found, and persisted to one on
+/// [`EmbeddingStore::flush`]. If the sidecar exists but covers less than the full data
+/// file - e.g. records were appended after the last `flush()` and the process never
+/// got to call it again before exiting - `open` tail-scans the uncovered bytes rather
+/// than adopting the sidecar as-is, so no stored record ever becomes unreachable.
+pub struct EmbeddingStore {
+    data_path: PathBuf,
+    index_path: PathBuf,
+    file: BufWriter<File>,
+    index: HashMap<String, u64>,
+    next_offset: u64,
+}
+
+impl EmbeddingStore {
+    /// Open (creating if necessary) a store rooted at `path`, e.g. `corpus.fastembed`.
+    ///
+    /// The index is read from a sidecar `<path>.idx` file if present, otherwise it is
+    /// rebuilt by scanning the data file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let data_path = path.as_ref().to_path_buf();
+        let index_path = Self::index_path_for(&data_path);
+        let is_new = !data_path.exists();
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&data_path)?;
+
+        if is_new {
+            file.write_all(&[FORMAT_VERSION])?;
+        } else {
+            Self::check_version(&data_path)?;
+        }
+
+        // `File` opened with `.append(true)` keeps its read/write cursor wherever the
+        // last operation left it (writes still land at EOF via `O_APPEND`, but the
+        // cursor itself is not moved there automatically) - seek to the true end once so
+        // `next_offset` starts off accurate.
+        let next_offset = file.seek(SeekFrom::End(0))?;
+
+        let index = if index_path.exists() {
+            let persisted = Self::load_index(&index_path)?;
+            if persisted.covered_offset < next_offset {
+                // The sidecar was written by an earlier session and the data file has
+                // since grown past what it covers - a crash or a missing `flush()`
+                // call before drop left trailing records unindexed. Tail-scan just the
+                // uncovered bytes rather than silently losing access to them.
+                Self::scan_records_from(&data_path, persisted.covered_offset, persisted.index)?
+            } else {
+                persisted.index
+            }
+        } else {
+            Self::rebuild_index(&data_path)?
+        };
+
+        Ok(Self {
+            data_path,
+            index_path,
+            file: BufWriter::new(file),
+            index,
+            next_offset,
+        })
+    }
+
+    /// Append a batch of `(id, embedding)` pairs, updating the in-memory index.
+    ///
+    /// Call [`EmbeddingStore::flush`] to persist the index so it survives a restart.
+    pub fn store_embeddings(&mut self, embeddings: &[(&str, Vec<f32>)]) -> io::Result<()> {
+        for (id, embedding) in embeddings {
+            let record = Record {
+                id: (*id).to_owned(),
+                embedding: embedding.clone(),
+            };
+            let payload = Self::encode(&record)?;
+
+            let offset = self.next_offset;
+            self.file.write_all(&(payload.len() as u64).to_le_bytes())?;
+            self.file.write_all(&payload)?;
+            self.next_offset += 8 + payload.len() as u64;
+            self.index.insert(record.id, offset);
+        }
+        Ok(())
+    }
+
+    /// Fetch a single embedding by id, without scanning the data file.
+    pub fn get(&mut self, id: &str) -> io::Result<Option<Vec<f32>>> {
+        let Some(&offset) = self.index.get(id) else {
+            return Ok(None);
+        };
+        self.file.flush()?;
+        let mut file = File::open(&self.data_path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let record = Self::read_record(&mut file)?;
+        Ok(Some(record.embedding))
+    }
+
+    /// Flush buffered writes and persist the `id -> offset` index to its sidecar file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let persisted = PersistedIndex {
+            covered_offset: self.next_offset,
+            index: self.index.clone(),
+        };
+        let index_bytes = bincode::serialize(&persisted)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        std::fs::write(&self.index_path, index_bytes)
+    }
+
+    /// Stream over all `(id, embedding)` records in the store, in on-disk order.
+    pub fn iter(&mut self) -> io::Result<EmbeddingStoreIter> {
+        self.file.flush()?;
+        let mut file = File::open(&self.data_path)?;
+        file.seek(SeekFrom::Start(1))?; // skip the version byte
+        Ok(EmbeddingStoreIter {
+            reader: BufReader::new(file),
+        })
+    }
+
+    fn index_path_for(data_path: &Path) -> PathBuf {
+        let mut index_path = data_path.as_os_str().to_owned();
+        index_path.push(".idx");
+        PathBuf::from(index_path)
+    }
+
+    fn check_version(data_path: &Path) -> io::Result<()> {
+        let mut file = File::open(data_path)?;
+        let mut version = [0u8; 1];
+        file.read_exact(&mut version)?;
+        if version[0] != FORMAT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "unsupported embedding store format version {} (expected {FORMAT_VERSION})",
+                    version[0]
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn load_index(index_path: &Path) -> io::Result<PersistedIndex> {
+        let bytes = std::fs::read(index_path)?;
+        bincode::deserialize(&bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    fn rebuild_index(data_path: &Path) -> io::Result<HashMap<String, u64>> {
+        Self::scan_records_from(data_path, 1, HashMap::new())
+    }
+
+    /// Scan records starting at `offset` (a byte offset already known to land on a
+    /// record boundary) to the end of the data file, inserting them into `index`.
+    fn scan_records_from(
+        data_path: &Path,
+        offset: u64,
+        mut index: HashMap<String, u64>,
+    ) -> io::Result<HashMap<String, u64>> {
+        let mut file = File::open(data_path)?;
+        file.seek(SeekFrom::Start(offset))?;
+
+        loop {
+            let offset = file.stream_position()?;
+            match Self::read_record(&mut file) {
+                Ok(record) => {
+                    index.insert(record.id, offset);
+                }
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(index)
+    }
+
+    fn encode(record: &Record) -> io::Result<Vec<u8>> {
+        bincode::serialize(record).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    fn read_record(reader: &mut impl Read) -> io::Result<Record> {
+        let mut len_bytes = [0u8; 8];
+        reader.read_exact(&mut len_bytes)?;
+        let len = u64::from_le_bytes(len_bytes) as usize;
+
+        let mut payload = vec![0u8; len];
+        reader.read_exact(&mut payload)?;
+        bincode::deserialize(&payload)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// Lazy iterator over all `(id, embedding)` records in an [`EmbeddingStore`].
+pub struct EmbeddingStoreIter {
+    reader: BufReader<File>,
+}
+
+impl Iterator for EmbeddingStoreIter {
+    type Item = io::Result<(String, Vec<f32>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match EmbeddingStore::read_record(&mut self.reader) {
+            Ok(record) => Some(Ok((record.id, record.embedding))),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "fastembed-embedding-store-test-{name}-{}.bin",
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn round_trip_write_reopen_get() {
+        let path = temp_store_path("round-trip");
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(EmbeddingStore::index_path_for(&path));
+
+        {
+            let mut store = EmbeddingStore::open(&path).unwrap();
+            store
+                .store_embeddings(&[("a", vec![1.0, 2.0]), ("b", vec![3.0, 4.0])])
+                .unwrap();
+            store.flush().unwrap();
+        }
+
+        // Reopening an existing, non-empty store and appending more records must not
+        // corrupt the offsets recorded for the records written in this new session.
+        {
+            let mut store = EmbeddingStore::open(&path).unwrap();
+            store.store_embeddings(&[("c", vec![5.0, 6.0])]).unwrap();
+
+            assert_eq!(store.get("a").unwrap(), Some(vec![1.0, 2.0]));
+            assert_eq!(store.get("b").unwrap(), Some(vec![3.0, 4.0]));
+            assert_eq!(store.get("c").unwrap(), Some(vec![5.0, 6.0]));
+            assert_eq!(store.get("missing").unwrap(), None);
+        }
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(EmbeddingStore::index_path_for(&path));
+    }
+
+    #[test]
+    fn iter_sees_records_written_without_an_intervening_flush() {
+        let path = temp_store_path("iter-unflushed");
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(EmbeddingStore::index_path_for(&path));
+
+        let mut store = EmbeddingStore::open(&path).unwrap();
+        store
+            .store_embeddings(&[("a", vec![1.0, 2.0]), ("b", vec![3.0, 4.0])])
+            .unwrap();
+
+        // No `flush()` call here: `iter()` must still see both records.
+        let records = store
+            .iter()
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            records,
+            vec![
+                ("a".to_owned(), vec![1.0, 2.0]),
+                ("b".to_owned(), vec![3.0, 4.0]),
+            ]
+        );
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(EmbeddingStore::index_path_for(&path));
+    }
+
+    #[test]
+    fn reopen_after_unflushed_write_recovers_trailing_records() {
+        let path = temp_store_path("unflushed-tail");
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(EmbeddingStore::index_path_for(&path));
+
+        {
+            let mut store = EmbeddingStore::open(&path).unwrap();
+            store.store_embeddings(&[("a", vec![1.0, 2.0])]).unwrap();
+            // Sidecar now covers only "a".
+            store.flush().unwrap();
+            // Appended but the process exits (crash, panic, no `flush()`) before the
+            // sidecar is updated to cover it.
+            store.store_embeddings(&[("b", vec![3.0, 4.0])]).unwrap();
+        }
+
+        // The stale sidecar must not make "b" permanently unreachable.
+        {
+            let mut store = EmbeddingStore::open(&path).unwrap();
+            assert_eq!(store.get("a").unwrap(), Some(vec![1.0, 2.0]));
+            assert_eq!(store.get("b").unwrap(), Some(vec![3.0, 4.0]));
+        }
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(EmbeddingStore::index_path_for(&path));
+    }
+}
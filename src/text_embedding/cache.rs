@@ -0,0 +1,126 @@
+//! A bounded in-memory cache of computed embedding vectors.
+
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+use lru::LruCache;
+
+use crate::{pooling::Pooling, QuantizationMode};
+
+/// Key identifying a cached embedding: the normalized input text together with the
+/// pooling/quantization configuration it was computed under.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    text: String,
+    pooling: Option<Pooling>,
+    quantization: QuantizationMode,
+}
+
+/// Thread-safe, bounded LRU cache of computed embedding vectors.
+///
+/// Keyed on the normalized input text plus the pooling/quantization configuration used
+/// to compute it, so results from different configurations never collide. `embed` splits
+/// its batch into cache hits and misses, runs the session only on the misses, then
+/// populates the cache and reassembles results in the original order.
+pub(crate) struct EmbeddingCache {
+    inner: Mutex<LruCache<CacheKey, Vec<f32>>>,
+}
+
+impl EmbeddingCache {
+    /// Create a new cache with the given capacity, or `None` if `capacity` is zero.
+    pub(crate) fn new(capacity: usize) -> Option<Self> {
+        NonZeroUsize::new(capacity).map(|capacity| Self {
+            inner: Mutex::new(LruCache::new(capacity)),
+        })
+    }
+
+    pub(crate) fn get(
+        &self,
+        text: &str,
+        pooling: Option<Pooling>,
+        quantization: QuantizationMode,
+    ) -> Option<Vec<f32>> {
+        let key = Self::key(text, pooling, quantization);
+        self.inner
+            .lock()
+            .expect("embedding cache mutex poisoned")
+            .get(&key)
+            .cloned()
+    }
+
+    pub(crate) fn put(
+        &self,
+        text: &str,
+        pooling: Option<Pooling>,
+        quantization: QuantizationMode,
+        embedding: Vec<f32>,
+    ) {
+        let key = Self::key(text, pooling, quantization);
+        self.inner
+            .lock()
+            .expect("embedding cache mutex poisoned")
+            .put(key, embedding);
+    }
+
+    fn key(text: &str, pooling: Option<Pooling>, quantization: QuantizationMode) -> CacheKey {
+        CacheKey {
+            text: text.trim().to_owned(),
+            pooling,
+            quantization,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_capacity_disables_the_cache() {
+        assert!(EmbeddingCache::new(0).is_none());
+    }
+
+    #[test]
+    fn get_put_round_trip() {
+        let cache = EmbeddingCache::new(2).unwrap();
+        assert_eq!(cache.get("hello", None, QuantizationMode::None), None);
+
+        cache.put("hello", None, QuantizationMode::None, vec![1.0, 2.0]);
+        assert_eq!(
+            cache.get("hello", None, QuantizationMode::None),
+            Some(vec![1.0, 2.0])
+        );
+    }
+
+    #[test]
+    fn different_pooling_or_quantization_does_not_collide() {
+        let cache = EmbeddingCache::new(4).unwrap();
+        cache.put("hello", Some(Pooling::Cls), QuantizationMode::None, vec![1.0]);
+        cache.put("hello", Some(Pooling::Mean), QuantizationMode::None, vec![2.0]);
+
+        assert_eq!(
+            cache.get("hello", Some(Pooling::Cls), QuantizationMode::None),
+            Some(vec![1.0])
+        );
+        assert_eq!(
+            cache.get("hello", Some(Pooling::Mean), QuantizationMode::None),
+            Some(vec![2.0])
+        );
+        assert_eq!(cache.get("hello", None, QuantizationMode::None), None);
+    }
+
+    #[test]
+    fn eviction_at_capacity_drops_the_least_recently_used_entry() {
+        let cache = EmbeddingCache::new(2).unwrap();
+        cache.put("a", None, QuantizationMode::None, vec![1.0]);
+        cache.put("b", None, QuantizationMode::None, vec![2.0]);
+        // Touch "a" so "b" becomes the least recently used entry.
+        assert_eq!(cache.get("a", None, QuantizationMode::None), Some(vec![1.0]));
+
+        cache.put("c", None, QuantizationMode::None, vec![3.0]);
+
+        assert_eq!(cache.get("b", None, QuantizationMode::None), None);
+        assert_eq!(cache.get("a", None, QuantizationMode::None), Some(vec![1.0]));
+        assert_eq!(cache.get("c", None, QuantizationMode::None), Some(vec![3.0]));
+    }
+}
@@ -1,16 +1,24 @@
 //! Initialization options for the text embedding models.
 //!
 
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use ort::{
+    execution_providers::ExecutionProviderDispatch,
+    session::{builder::GraphOptimizationLevel, Session},
+};
+use serde::Deserialize;
+use tokenizers::Tokenizer;
+
 use crate::{
     common::TokenizerFiles,
     init::{HasMaxLength, InitOptionsWithLength},
     pooling::Pooling,
     EmbeddingModel, OutputKey, QuantizationMode,
 };
-use ort::{execution_providers::ExecutionProviderDispatch, session::Session};
-use tokenizers::Tokenizer;
 
-use super::DEFAULT_MAX_LENGTH;
+use super::{cache::EmbeddingCache, DEFAULT_MAX_LENGTH};
 
 impl HasMaxLength for EmbeddingModel {
     const MAX_LENGTH: usize = DEFAULT_MAX_LENGTH;
@@ -27,6 +35,12 @@ pub type TextInitOptions = InitOptionsWithLength<EmbeddingModel>;
 pub struct InitOptionsUserDefined {
     pub execution_providers: Vec<ExecutionProviderDispatch>,
     pub max_length: usize,
+    pub graph_optimization_level: Option<GraphOptimizationLevel>,
+    pub intra_threads: Option<usize>,
+    pub inter_threads: Option<usize>,
+    pub extensions: bool,
+    pub config_entries: Vec<(String, String)>,
+    pub cache_capacity: usize,
 }
 
 impl InitOptionsUserDefined {
@@ -48,6 +62,62 @@ impl InitOptionsUserDefined {
         self.max_length = max_length;
         self
     }
+
+    /// Set the ONNX Runtime graph optimization level applied to the session.
+    ///
+    /// Defaults to ort's own default (`Level3`) when left unset.
+    pub fn with_graph_optimization_level(
+        mut self,
+        graph_optimization_level: GraphOptimizationLevel,
+    ) -> Self {
+        self.graph_optimization_level = Some(graph_optimization_level);
+        self
+    }
+
+    /// Bound the number of threads used to parallelize execution within a node.
+    ///
+    /// Left unset, ort picks this based on the available hardware.
+    pub fn with_intra_threads(mut self, intra_threads: usize) -> Self {
+        self.intra_threads = Some(intra_threads);
+        self
+    }
+
+    /// Bound the number of threads used to parallelize execution across nodes.
+    ///
+    /// Left unset, ort picks this based on the available hardware.
+    pub fn with_inter_threads(mut self, inter_threads: usize) -> Self {
+        self.inter_threads = Some(inter_threads);
+        self
+    }
+
+    /// Enable the onnxruntime-extensions custom op library on the session.
+    ///
+    /// Needed for "tokenizer-in-graph" models whose ONNX graph relies on custom
+    /// operators (e.g. string/tokenization ops) rather than `tokenizer_files`.
+    /// Requires the `ort-extensions` feature to be compiled in.
+    pub fn with_extensions(mut self, extensions: bool) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Forward arbitrary key/value configuration entries to the ort session builder.
+    ///
+    /// An escape hatch for ORT knobs the crate does not model explicitly (memory-arena
+    /// behavior, disabled optimizers, provider-specific keys, ...). Applied after the
+    /// typed options above, so entries set here can override their defaults.
+    pub fn with_config_entries(mut self, config_entries: Vec<(String, String)>) -> Self {
+        self.config_entries = config_entries;
+        self
+    }
+
+    /// Cache up to `cache_capacity` computed embeddings in an in-memory LRU, keyed on the
+    /// normalized input text and the pooling/quantization configuration.
+    ///
+    /// A capacity of `0` (the default) disables the cache.
+    pub fn with_cache_capacity(mut self, cache_capacity: usize) -> Self {
+        self.cache_capacity = cache_capacity;
+        self
+    }
 }
 
 impl Default for InitOptionsUserDefined {
@@ -55,6 +125,12 @@ impl Default for InitOptionsUserDefined {
         Self {
             execution_providers: Default::default(),
             max_length: DEFAULT_MAX_LENGTH,
+            graph_optimization_level: None,
+            intra_threads: None,
+            inter_threads: None,
+            extensions: false,
+            config_entries: Vec::new(),
+            cache_capacity: 0,
         }
     }
 }
@@ -67,10 +143,60 @@ impl From<TextInitOptions> for InitOptionsUserDefined {
         InitOptionsUserDefined {
             execution_providers: options.execution_providers,
             max_length: options.max_length,
+            graph_optimization_level: None,
+            intra_threads: None,
+            inter_threads: None,
+            extensions: false,
+            config_entries: Vec::new(),
+            cache_capacity: 0,
         }
     }
 }
 
+impl InitOptionsUserDefined {
+    /// Apply the graph optimization level and thread settings onto a session builder.
+    ///
+    /// Called while constructing the `Session` for `TextEmbedding`; options left unset
+    /// fall through to ort's own defaults.
+    pub(crate) fn configure_session_builder(
+        &self,
+        mut builder: ort::session::builder::SessionBuilder,
+    ) -> ort::Result<ort::session::builder::SessionBuilder> {
+        if let Some(graph_optimization_level) = self.graph_optimization_level {
+            builder = builder.with_optimization_level(graph_optimization_level)?;
+        }
+        if let Some(intra_threads) = self.intra_threads {
+            builder = builder.with_intra_threads(intra_threads)?;
+        }
+        if let Some(inter_threads) = self.inter_threads {
+            builder = builder.with_inter_threads(inter_threads)?;
+        }
+        if self.extensions {
+            builder = Self::register_extensions(builder)?;
+        }
+        for (key, value) in &self.config_entries {
+            builder = builder.with_config_entry(key, value)?;
+        }
+        Ok(builder)
+    }
+
+    #[cfg(feature = "ort-extensions")]
+    fn register_extensions(
+        builder: ort::session::builder::SessionBuilder,
+    ) -> ort::Result<ort::session::builder::SessionBuilder> {
+        builder.with_extensions()
+    }
+
+    #[cfg(not(feature = "ort-extensions"))]
+    fn register_extensions(
+        _builder: ort::session::builder::SessionBuilder,
+    ) -> ort::Result<ort::session::builder::SessionBuilder> {
+        Err(ort::Error::new(
+            "onnxruntime-extensions support requires fastembed to be built with the `ort-extensions` feature",
+        ))
+    }
+}
+
 /// Enum for the source of the onnx file
 ///
 /// User-defined models can either be in memory or on disk.
@@ -136,6 +262,165 @@ impl UserDefinedEmbeddingModel {
     }
 }
 
+/// Paths to the files that make up a model's tokenizer in a declarative model config
+/// file. Relative paths are resolved against the config file's own directory.
+#[derive(Debug, Clone, Deserialize)]
+struct TokenizerFilesConfig {
+    tokenizer_file: PathBuf,
+    config_file: PathBuf,
+    special_tokens_map_file: PathBuf,
+    tokenizer_config_file: PathBuf,
+}
+
+/// Serde mirror of a declarative model config file (YAML or JSON).
+///
+/// Describes a [`UserDefinedEmbeddingModel`] and its [`InitOptionsUserDefined`] as data,
+/// so applications can ship model definitions rather than recompiling them.
+#[derive(Debug, Clone, Deserialize)]
+struct ModelConfigFile {
+    onnx_file: PathBuf,
+    tokenizer_files: TokenizerFilesConfig,
+    #[serde(default)]
+    pooling: Option<Pooling>,
+    #[serde(default)]
+    quantization: Option<QuantizationMode>,
+    #[serde(default)]
+    output_key: Option<OutputKey>,
+    #[serde(default = "ModelConfigFile::default_max_length")]
+    max_length: usize,
+    #[serde(default)]
+    execution_providers: Vec<String>,
+}
+
+impl ModelConfigFile {
+    fn default_max_length() -> usize {
+        DEFAULT_MAX_LENGTH
+    }
+}
+
+impl UserDefinedEmbeddingModel {
+    /// Load a [`UserDefinedEmbeddingModel`] and its [`InitOptionsUserDefined`] from a
+    /// declarative YAML or JSON config file.
+    ///
+    /// The config's `onnx_file` and `tokenizer_files` paths are resolved relative to the
+    /// directory containing `path`. Files are selected as JSON when `path` has a `.json`
+    /// extension, and YAML otherwise. Returns a single error listing every referenced
+    /// file (the ONNX model and all tokenizer files) that is missing or unreadable.
+    pub fn from_config_file(
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<(Self, InitOptionsUserDefined)> {
+        let path = path.as_ref();
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read model config file {}", path.display()))?;
+
+        let config: ModelConfigFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&raw)
+                .with_context(|| format!("failed to parse {} as JSON", path.display()))?,
+            _ => serde_yaml::from_str(&raw)
+                .with_context(|| format!("failed to parse {} as YAML", path.display()))?,
+        };
+
+        let onnx_path = base_dir.join(&config.onnx_file);
+        let (tokenizer_files, mut errors) =
+            Self::read_tokenizer_files(base_dir, &config.tokenizer_files);
+        match std::fs::File::open(&onnx_path) {
+            Ok(_) if onnx_path.is_file() => {}
+            Ok(_) => errors.push(format!("onnx_file: {} (not a file)", onnx_path.display())),
+            Err(err) => errors.push(format!("onnx_file: {} ({err})", onnx_path.display())),
+        }
+        if !errors.is_empty() {
+            anyhow::bail!(
+                "missing or unreadable files referenced by model config {}:\n{}",
+                path.display(),
+                errors.join("\n")
+            );
+        }
+        let tokenizer_files = tokenizer_files.expect("no errors means every file was read");
+
+        let mut model = UserDefinedEmbeddingModel::new(onnx_path, tokenizer_files);
+        if let Some(quantization) = config.quantization {
+            model = model.with_quantization(quantization);
+        }
+        if let Some(pooling) = config.pooling {
+            model = model.with_pooling(pooling);
+        }
+        model.output_key = config.output_key;
+
+        let mut options = InitOptionsUserDefined::new().with_max_length(config.max_length);
+        if !config.execution_providers.is_empty() {
+            options = options.with_execution_providers(
+                config
+                    .execution_providers
+                    .iter()
+                    .map(|name| Self::execution_provider_from_name(name))
+                    .collect::<anyhow::Result<Vec<_>>>()?,
+            );
+        }
+
+        Ok((model, options))
+    }
+
+    /// Read every file referenced by `tokenizer_files`, collecting all failures rather
+    /// than stopping at the first missing or unreadable one.
+    fn read_tokenizer_files(
+        base_dir: &Path,
+        tokenizer_files: &TokenizerFilesConfig,
+    ) -> (Option<TokenizerFiles>, Vec<String>) {
+        let referenced = [
+            ("tokenizer_file", &tokenizer_files.tokenizer_file),
+            ("config_file", &tokenizer_files.config_file),
+            (
+                "special_tokens_map_file",
+                &tokenizer_files.special_tokens_map_file,
+            ),
+            (
+                "tokenizer_config_file",
+                &tokenizer_files.tokenizer_config_file,
+            ),
+        ];
+
+        let mut contents = std::collections::HashMap::new();
+        let mut errors = Vec::new();
+        for (field, relative) in referenced {
+            let resolved = base_dir.join(relative);
+            match std::fs::read(&resolved) {
+                Ok(bytes) => {
+                    contents.insert(field, bytes);
+                }
+                Err(err) => errors.push(format!("{field}: {} ({err})", resolved.display())),
+            }
+        }
+
+        if !errors.is_empty() {
+            return (None, errors);
+        }
+
+        (
+            Some(TokenizerFiles {
+                tokenizer_file: contents.remove("tokenizer_file").unwrap(),
+                config_file: contents.remove("config_file").unwrap(),
+                special_tokens_map_file: contents.remove("special_tokens_map_file").unwrap(),
+                tokenizer_config_file: contents.remove("tokenizer_config_file").unwrap(),
+            }),
+            errors,
+        )
+    }
+
+    fn execution_provider_from_name(name: &str) -> anyhow::Result<ExecutionProviderDispatch> {
+        match name {
+            "cpu" => Ok(ort::execution_providers::CPUExecutionProvider::default().build()),
+            "cuda" => Ok(ort::execution_providers::CUDAExecutionProvider::default().build()),
+            "tensorrt" => {
+                Ok(ort::execution_providers::TensorRTExecutionProvider::default().build())
+            }
+            "coreml" => Ok(ort::execution_providers::CoreMLExecutionProvider::default().build()),
+            other => anyhow::bail!("unknown execution provider in model config: {other}"),
+        }
+    }
+}
+
 /// Rust representation of the TextEmbedding model
 pub struct TextEmbedding {
     pub tokenizer: Tokenizer,
@@ -144,4 +429,81 @@ pub struct TextEmbedding {
     pub(crate) need_token_type_ids: bool,
     pub(crate) quantization: QuantizationMode,
     pub(crate) output_key: Option<OutputKey>,
+    pub(crate) cache: Option<EmbeddingCache>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "fastembed-from-config-file-test-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn missing_files_are_all_reported_together() {
+        let dir = temp_dir("missing-files");
+        std::fs::write(dir.join("model.onnx"), b"not a real model").unwrap();
+        // "config.json" and "special_tokens_map.json" are deliberately not written.
+        std::fs::write(dir.join("tokenizer.json"), b"{}").unwrap();
+        std::fs::write(dir.join("tokenizer_config.json"), b"{}").unwrap();
+
+        let config_path = dir.join("model.yaml");
+        std::fs::write(
+            &config_path,
+            r#"
+onnx_file: model.onnx
+tokenizer_files:
+  tokenizer_file: tokenizer.json
+  config_file: config.json
+  special_tokens_map_file: special_tokens_map.json
+  tokenizer_config_file: tokenizer_config.json
+"#,
+        )
+        .unwrap();
+
+        let err = UserDefinedEmbeddingModel::from_config_file(&config_path).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("config_file"), "{message}");
+        assert!(message.contains("special_tokens_map_file"), "{message}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn missing_onnx_file_is_reported_alongside_missing_tokenizer_files() {
+        let dir = temp_dir("missing-onnx-and-tokenizer");
+        // Only tokenizer.json and the config file itself are written; the ONNX model and
+        // the other tokenizer files are missing.
+        std::fs::write(dir.join("tokenizer.json"), b"{}").unwrap();
+
+        let config_path = dir.join("model.yaml");
+        std::fs::write(
+            &config_path,
+            r#"
+onnx_file: model.onnx
+tokenizer_files:
+  tokenizer_file: tokenizer.json
+  config_file: config.json
+  special_tokens_map_file: special_tokens_map.json
+  tokenizer_config_file: tokenizer_config.json
+"#,
+        )
+        .unwrap();
+
+        let err = UserDefinedEmbeddingModel::from_config_file(&config_path).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("onnx_file"), "{message}");
+        assert!(message.contains("config_file"), "{message}");
+        assert!(message.contains("special_tokens_map_file"), "{message}");
+        assert!(message.contains("tokenizer_config_file"), "{message}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
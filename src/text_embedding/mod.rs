@@ -0,0 +1,226 @@
+//! The `TextEmbedding` model: construction from a user-defined ONNX model, and the
+//! batched inference pipeline used by [`TextEmbedding::embed`].
+
+mod cache;
+mod init;
+
+pub use init::{
+    InitOptionsUserDefined, OnnxSource, TextEmbedding, TextInitOptions, UserDefinedEmbeddingModel,
+};
+
+use anyhow::{anyhow, Result};
+use ndarray::{Array2, ArrayViewD, Axis};
+use ort::{session::Session, value::Value};
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+use cache::EmbeddingCache;
+
+/// Default maximum input sequence length, in tokens, used by the built-in models.
+pub(crate) const DEFAULT_MAX_LENGTH: usize = 512;
+
+/// Default batch size used by [`TextEmbedding::embed`] when the caller does not specify one.
+const DEFAULT_BATCH_SIZE: usize = 256;
+
+impl TextEmbedding {
+    /// Construct a `TextEmbedding` from a user-provided ONNX model and tokenizer files.
+    pub fn try_new_from_user_defined(
+        model: UserDefinedEmbeddingModel,
+        options: InitOptionsUserDefined,
+    ) -> Result<Self> {
+        let mut tokenizer = Tokenizer::from_bytes(&model.tokenizer_files.tokenizer_file)
+            .map_err(|err| anyhow!("failed to load tokenizer: {err}"))?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: options.max_length,
+                ..Default::default()
+            }))
+            .map_err(|err| anyhow!("failed to configure tokenizer truncation: {err}"))?;
+
+        let builder =
+            Session::builder()?.with_execution_providers(options.execution_providers.clone())?;
+        // Graph optimization level and thread counts are applied here, onto the same
+        // builder used to commit the model.
+        let builder = options.configure_session_builder(builder)?;
+
+        let session = match model.onnx_source {
+            OnnxSource::Memory(bytes) => builder.commit_from_memory(&bytes)?,
+            OnnxSource::File(path) => builder.commit_from_file(path)?,
+        };
+
+        let need_token_type_ids = session
+            .inputs
+            .iter()
+            .any(|input| input.name == "token_type_ids");
+
+        Ok(Self {
+            tokenizer,
+            pooling: model.pooling,
+            session,
+            need_token_type_ids,
+            quantization: model.quantization,
+            output_key: model.output_key,
+            cache: EmbeddingCache::new(options.cache_capacity),
+        })
+    }
+
+    /// Compute an embedding vector for each text in `texts`.
+    ///
+    /// Inputs are first checked against the in-memory cache (see
+    /// [`InitOptionsUserDefined::with_cache_capacity`]); the ONNX session only runs on
+    /// the resulting cache misses, processed in chunks of `batch_size` (default
+    /// [`DEFAULT_BATCH_SIZE`]). Results are returned in the same order as `texts`.
+    pub fn embed<S: AsRef<str>>(
+        &self,
+        texts: Vec<S>,
+        batch_size: Option<usize>,
+    ) -> Result<Vec<Vec<f32>>> {
+        let batch_size = batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1);
+
+        let mut results: Vec<Option<Vec<f32>>> = vec![None; texts.len()];
+        let mut miss_indices = Vec::new();
+        let mut miss_texts = Vec::new();
+
+        for (index, text) in texts.iter().enumerate() {
+            let text = text.as_ref();
+            let cached = self
+                .cache
+                .as_ref()
+                .and_then(|cache| cache.get(text, self.pooling, self.quantization));
+            match cached {
+                Some(embedding) => results[index] = Some(embedding),
+                None => {
+                    miss_indices.push(index);
+                    miss_texts.push(text.to_owned());
+                }
+            }
+        }
+
+        for (chunk_no, indices) in miss_indices.chunks(batch_size).enumerate() {
+            let start = chunk_no * batch_size;
+            let texts = &miss_texts[start..start + indices.len()];
+
+            let embeddings = self.embed_batch(texts)?;
+            for (&index, (text, embedding)) in indices.iter().zip(texts.iter().zip(embeddings)) {
+                if let Some(cache) = &self.cache {
+                    cache.put(text, self.pooling, self.quantization, embedding.clone());
+                }
+                results[index] = Some(embedding);
+            }
+        }
+
+        Ok(results
+            .into_iter()
+            .map(|embedding| {
+                embedding.expect("every index is filled by either the cache or a batch run")
+            })
+            .collect())
+    }
+
+    /// Run the ONNX session on a single batch of texts that were not found in the cache.
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.iter().map(String::as_str).collect::<Vec<_>>(), true)
+            .map_err(|err| anyhow!("failed to tokenize batch: {err}"))?;
+
+        let batch_len = encodings.len();
+        let seq_len = encodings.first().map(|e| e.len()).unwrap_or_default();
+
+        let mut input_ids = Array2::<i64>::zeros((batch_len, seq_len));
+        let mut attention_mask = Array2::<i64>::zeros((batch_len, seq_len));
+        let mut token_type_ids = Array2::<i64>::zeros((batch_len, seq_len));
+
+        for (row, encoding) in encodings.iter().enumerate() {
+            for (col, &id) in encoding.get_ids().iter().enumerate() {
+                input_ids[[row, col]] = id as i64;
+            }
+            for (col, &mask) in encoding.get_attention_mask().iter().enumerate() {
+                attention_mask[[row, col]] = mask as i64;
+            }
+            if self.need_token_type_ids {
+                for (col, &type_id) in encoding.get_type_ids().iter().enumerate() {
+                    token_type_ids[[row, col]] = type_id as i64;
+                }
+            }
+        }
+
+        let mut session_inputs = ort::inputs![
+            "input_ids" => Value::from_array(input_ids)?,
+            "attention_mask" => Value::from_array(attention_mask.clone())?,
+        ]?;
+        if self.need_token_type_ids {
+            session_inputs.push((
+                "token_type_ids".into(),
+                Value::from_array(token_type_ids)?.into(),
+            ));
+        }
+
+        let outputs = self.session.run(session_inputs)?;
+        let output: ArrayViewD<f32> = self.select_output(&outputs)?.try_extract_tensor()?;
+
+        let pooled = self.pool(output, &attention_mask)?;
+        Ok(pooled.rows().into_iter().map(|row| row.to_vec()).collect())
+    }
+
+    fn select_output<'s>(
+        &self,
+        outputs: &'s ort::session::SessionOutputs,
+    ) -> Result<&'s ort::value::DynValue> {
+        if let Some(output_key) = &self.output_key {
+            outputs
+                .get(output_key.as_str())
+                .ok_or_else(|| anyhow!("model has no output named {output_key:?}"))
+        } else {
+            outputs
+                .values()
+                .next()
+                .ok_or_else(|| anyhow!("model produced no outputs"))
+        }
+    }
+
+    /// Pool the model's token-level output into one vector per input, using `pooling` if
+    /// set, or passing the output through unchanged when it is already a single vector
+    /// per input (rank 2).
+    fn pool(&self, output: ArrayViewD<f32>, attention_mask: &Array2<i64>) -> Result<Array2<f32>> {
+        use crate::pooling::Pooling;
+
+        match (&self.pooling, output.ndim()) {
+            (_, 2) => output
+                .into_dimensionality()
+                .map_err(|err| anyhow!("unexpected output shape: {err}")),
+            (Some(Pooling::Cls), 3) => Ok(output.index_axis(Axis(1), 0).to_owned()),
+            (Some(Pooling::Mean), 3) => {
+                let output = output
+                    .into_dimensionality::<ndarray::Ix3>()
+                    .map_err(|err| anyhow!("unexpected output shape: {err}"))?;
+                let (batch_len, seq_len, hidden) = output.dim();
+                let mut pooled = Array2::<f32>::zeros((batch_len, hidden));
+                for row in 0..batch_len {
+                    let mut count = 0f32;
+                    for col in 0..seq_len {
+                        if attention_mask[[row, col]] == 0 {
+                            continue;
+                        }
+                        count += 1.0;
+                        for h in 0..hidden {
+                            pooled[[row, h]] += output[[row, col, h]];
+                        }
+                    }
+                    if count > 0.0 {
+                        for h in 0..hidden {
+                            pooled[[row, h]] /= count;
+                        }
+                    }
+                }
+                Ok(pooled)
+            }
+            (None, ndim) => Err(anyhow!(
+                "model output has rank {ndim} but no pooling strategy was configured"
+            )),
+            (Some(_), ndim) => Err(anyhow!(
+                "unsupported output rank {ndim} for the configured pooling strategy"
+            )),
+        }
+    }
+}